@@ -0,0 +1,107 @@
+//! SQLite-backed store of which commits have already been notified about,
+//! one row per commit per repo.
+
+use crate::CommitInfo;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::Path;
+use std::time::Duration;
+
+const SCHEMA_VERSION: i64 = 1;
+
+pub struct Db {
+    conn: Connection,
+}
+
+impl Db {
+    /// Opens (creating if needed) the shared `gitmon.db`. Multiple workers
+    /// each hold their own connection to this same file, so WAL mode plus a
+    /// busy timeout are required — otherwise concurrent writers hit
+    /// `SQLITE_BUSY` immediately instead of waiting their turn.
+    pub fn open(path: &Path) -> rusqlite::Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.busy_timeout(Duration::from_secs(5))?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        let db = Db { conn };
+        db.migrate()?;
+        Ok(db)
+    }
+
+    fn migrate(&self) -> rusqlite::Result<()> {
+        self.conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS schema_version (version INTEGER NOT NULL);
+             CREATE TABLE IF NOT EXISTS repos (
+                 id INTEGER PRIMARY KEY,
+                 url TEXT NOT NULL UNIQUE
+             );
+             CREATE TABLE IF NOT EXISTS seen_commits (
+                 sha TEXT NOT NULL,
+                 repo_id INTEGER NOT NULL REFERENCES repos(id),
+                 author TEXT NOT NULL,
+                 date TEXT NOT NULL,
+                 summary TEXT NOT NULL,
+                 change_id TEXT,
+                 notified_at TEXT NOT NULL,
+                 PRIMARY KEY (sha, repo_id)
+             );",
+        )?;
+
+        let version: i64 = self
+            .conn
+            .query_row("SELECT version FROM schema_version LIMIT 1", [], |row| {
+                row.get(0)
+            })
+            .optional()?
+            .unwrap_or(0);
+
+        // No migrations beyond the initial schema yet; this is the hook
+        // future schema changes run through.
+        if version < SCHEMA_VERSION {
+            self.conn.execute("DELETE FROM schema_version", [])?;
+            self.conn.execute(
+                "INSERT INTO schema_version (version) VALUES (?1)",
+                params![SCHEMA_VERSION],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the id of the repo row for `url`, creating it if this is the
+    /// first time gitmon has seen it.
+    pub fn repo_id(&self, url: &str) -> rusqlite::Result<i64> {
+        self.conn
+            .execute("INSERT OR IGNORE INTO repos (url) VALUES (?1)", params![url])?;
+        self.conn
+            .query_row("SELECT id FROM repos WHERE url = ?1", params![url], |row| {
+                row.get(0)
+            })
+    }
+
+    pub fn is_notified(&self, repo_id: i64, sha: &str) -> rusqlite::Result<bool> {
+        self.conn
+            .query_row(
+                "SELECT 1 FROM seen_commits WHERE repo_id = ?1 AND sha = ?2",
+                params![repo_id, sha],
+                |_| Ok(()),
+            )
+            .optional()
+            .map(|row| row.is_some())
+    }
+
+    pub fn mark_notified(&self, repo_id: i64, commit: &CommitInfo) -> rusqlite::Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO seen_commits
+                 (sha, repo_id, author, date, summary, change_id, notified_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, datetime('now'))",
+            params![
+                commit.id,
+                repo_id,
+                commit.author,
+                commit.date,
+                commit.message,
+                commit.change_id,
+            ],
+        )?;
+        Ok(())
+    }
+}
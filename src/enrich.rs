@@ -0,0 +1,163 @@
+//! Optional enrichment of commit metadata via the GitHub/GitLab REST APIs:
+//! associated PR/MR, signature verification status, and the author's
+//! profile. `get_new_commits_since` only has what's in the local object, so
+//! this fills in the rest for hosts with an API token configured — and
+//! degrades to a no-op (not an error) when there's no token, the lookup
+//! misses, or the API rate-limits us, so the offline path keeps working.
+
+use crate::{BoxError, CommitInfo, RepoConfig};
+use log::debug;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+pub fn enrich_commits(repo: &RepoConfig, commits: &mut [CommitInfo], api_tokens: &HashMap<String, String>) {
+    let Some(host) = host_of(&repo.url) else {
+        return;
+    };
+    let Some(token) = api_tokens.get(&host) else {
+        return;
+    };
+    let Some((owner, name)) = owner_and_repo(&repo.url) else {
+        return;
+    };
+
+    for commit in commits.iter_mut() {
+        let result = match host.as_str() {
+            "github.com" => enrich_from_github(&owner, &name, token, commit),
+            "gitlab.com" => enrich_from_gitlab(&owner, &name, token, commit),
+            _ => Ok(()),
+        };
+        if let Err(e) = result {
+            debug!("Skipping enrichment for {}: {}", commit.id, e);
+        }
+    }
+}
+
+fn host_of(remote_url: &str) -> Option<String> {
+    let without_scheme = remote_url.split("://").nth(1).unwrap_or(remote_url);
+    without_scheme.split('/').next().map(|h| h.to_lowercase())
+}
+
+fn owner_and_repo(remote_url: &str) -> Option<(String, String)> {
+    let without_scheme = remote_url.split("://").nth(1)?;
+    let path = without_scheme.splitn(2, '/').nth(1)?;
+    let path = path.trim_end_matches(".git").trim_end_matches('/');
+    let mut parts = path.splitn(2, '/');
+    let owner = parts.next()?;
+    let name = parts.next()?;
+    Some((owner.to_string(), name.to_string()))
+}
+
+#[derive(Debug, Deserialize)]
+struct GithubCommit {
+    author: Option<GithubUser>,
+    commit: GithubCommitDetail,
+}
+
+#[derive(Debug, Deserialize)]
+struct GithubCommitDetail {
+    verification: Option<GithubVerification>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GithubVerification {
+    verified: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct GithubUser {
+    login: String,
+    avatar_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GithubPull {
+    number: u64,
+    title: String,
+}
+
+fn enrich_from_github(
+    owner: &str,
+    name: &str,
+    token: &str,
+    commit: &mut CommitInfo,
+) -> Result<(), BoxError> {
+    let detail: GithubCommit = ureq::get(&format!(
+        "https://api.github.com/repos/{}/{}/commits/{}",
+        owner, name, commit.id
+    ))
+    .set("Authorization", &format!("Bearer {}", token))
+    .set("Accept", "application/vnd.github+json")
+    .call()?
+    .into_json()?;
+
+    commit.verified = detail.commit.verification.map(|v| v.verified);
+    if let Some(author) = detail.author {
+        commit.author_login = Some(author.login);
+        commit.author_avatar_url = Some(author.avatar_url);
+    }
+
+    let pulls: Vec<GithubPull> = ureq::get(&format!(
+        "https://api.github.com/repos/{}/{}/commits/{}/pulls",
+        owner, name, commit.id
+    ))
+    .set("Authorization", &format!("Bearer {}", token))
+    .set("Accept", "application/vnd.github+json")
+    .call()?
+    .into_json()?;
+
+    if let Some(pull) = pulls.into_iter().next() {
+        commit.pr_number = Some(pull.number);
+        commit.pr_title = Some(pull.title);
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+struct GitlabCommit {
+    author_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitlabMergeRequest {
+    iid: u64,
+    title: String,
+}
+
+fn enrich_from_gitlab(
+    owner: &str,
+    name: &str,
+    token: &str,
+    commit: &mut CommitInfo,
+) -> Result<(), BoxError> {
+    let project = urlencoding_path(owner, name);
+
+    let detail: GitlabCommit = ureq::get(&format!(
+        "https://gitlab.com/api/v4/projects/{}/repository/commits/{}",
+        project, commit.id
+    ))
+    .set("PRIVATE-TOKEN", token)
+    .call()?
+    .into_json()?;
+    commit.author_login = Some(detail.author_name);
+
+    let merge_requests: Vec<GitlabMergeRequest> = ureq::get(&format!(
+        "https://gitlab.com/api/v4/projects/{}/repository/commits/{}/merge_requests",
+        project, commit.id
+    ))
+    .set("PRIVATE-TOKEN", token)
+    .call()?
+    .into_json()?;
+
+    if let Some(mr) = merge_requests.into_iter().next() {
+        commit.pr_number = Some(mr.iid);
+        commit.pr_title = Some(mr.title);
+    }
+
+    Ok(())
+}
+
+fn urlencoding_path(owner: &str, name: &str) -> String {
+    format!("{}%2F{}", owner, name)
+}
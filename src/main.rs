@@ -1,20 +1,17 @@
+mod db;
+mod enrich;
+mod notifier;
+mod webhook;
+
 use clap::Parser;
 use dirs;
 use git2::Repository;
-use lettre::{
-    message::{header::ContentType, Mailbox},
-    transport::smtp::authentication::Credentials,
-    Message, SmtpTransport, Transport,
-};
-use log::{debug, info};
-use serde::{Deserialize, Serialize};
+use log::{debug, error, info};
+use notifier::{build_notifiers, Notifier, NotifierConfig, Report};
+use serde::Deserialize;
 use sha1::{Digest, Sha1};
 use std::process::Command;
-use std::{
-    collections::{BTreeMap, HashMap},
-    fs,
-    path::PathBuf,
-};
+use std::{collections::HashMap, fs, path::PathBuf};
 
 #[derive(Parser, Debug)]
 #[command(name = "gitmon", version, author, about)]
@@ -27,22 +24,46 @@ struct Args {
 
     #[arg(short, long)]
     output: Option<PathBuf>,
+
+    #[command(subcommand)]
+    action: Option<Action>,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Action {
+    /// Listen for GitHub push webhooks instead of polling repos on a timer
+    Serve {
+        /// Address to bind the webhook HTTP server to
+        #[arg(long, default_value = "0.0.0.0:8787")]
+        bind: String,
+    },
 }
 
 #[derive(Debug, Deserialize)]
 struct Config {
-    repos: Vec<String>,
-    from: String,
-    to: String,
-    token: String,
+    repos: Vec<RepoConfig>,
+    notifiers: Vec<NotifierConfig>,
     template_path: Option<String>,
     cache_dir: Option<String>,
     max_commits: Option<usize>,
+    /// Inline each commit's unified diff in the report instead of only
+    /// linking out to a provider patch URL.
+    #[serde(default)]
+    include_diffs: bool,
+    /// Number of repos to clone/pull and walk concurrently.
+    concurrency: Option<usize>,
+    /// API tokens for commit metadata enrichment (PR/MR, verification
+    /// status, author profile), keyed by host (e.g. `github.com`).
+    #[serde(default)]
+    api_tokens: HashMap<String, String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Default)]
-struct State {
-    last_seen: BTreeMap<String, String>,
+#[derive(Debug, Clone, Deserialize)]
+struct RepoConfig {
+    url: String,
+    /// Shared secret GitHub signs webhook payloads with (`X-Hub-Signature-256`).
+    /// Only required when running in `serve` mode.
+    webhook_secret: Option<String>,
 }
 
 struct CommitInfo {
@@ -51,22 +72,18 @@ struct CommitInfo {
     author: String,
     message: String,
     change_id: Option<String>,
+    diff: Option<String>,
+    verified: Option<bool>,
+    author_login: Option<String>,
+    author_avatar_url: Option<String>,
+    pr_number: Option<u64>,
+    pr_title: Option<String>,
 }
 
-fn load_state(path: &PathBuf) -> State {
-    if path.exists() {
-        let data = fs::read_to_string(path).unwrap_or_default();
-        serde_json::from_str(&data).unwrap_or_default()
-    } else {
-        State::default()
-    }
-}
-
-fn save_state(state: &State, path: &PathBuf) {
-    if let Ok(json) = serde_json::to_string_pretty(state) {
-        fs::write(path, json).ok();
-    }
-}
+/// Error type for the fetch/enrich/notify pipeline. Needs `Send + Sync` (not
+/// just `std::error::Error`) because results carrying it are sent across
+/// the `fetch_all_repos` worker-thread channel.
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
 
 fn hash_repo_url(url: &str) -> String {
     let mut hasher = Sha1::new();
@@ -74,30 +91,23 @@ fn hash_repo_url(url: &str) -> String {
     format!("{:x}", hasher.finalize())
 }
 
-fn clone_or_update_repo(
-    remote_url: &str,
-    base_cache_dir: &PathBuf,
-) -> Result<PathBuf, git2::Error> {
+fn clone_or_update_repo(remote_url: &str, base_cache_dir: &PathBuf) -> Result<PathBuf, BoxError> {
     let repo_hash = hash_repo_url(remote_url);
     let repo_dir = base_cache_dir.join(repo_hash);
 
     if repo_dir.exists() {
         debug!("Pulling updates for {}", remote_url);
-        let output = Command::new("git")
-            .arg("-C")
-            .arg(&repo_dir)
-            .arg("pull")
-            .output();
-
-        match output {
-            Ok(out) if out.status.success() => debug!("Updated {}", remote_url),
-            Ok(out) => eprintln!(
-                "Git pull failed: {}\n{}",
+        let output = Command::new("git").arg("-C").arg(&repo_dir).arg("pull").output()?;
+
+        if !output.status.success() {
+            return Err(format!(
+                "git pull failed for {}: {}",
                 remote_url,
-                String::from_utf8_lossy(&out.stderr)
-            ),
-            Err(e) => eprintln!("Git pull error on {}: {}", remote_url, e),
+                String::from_utf8_lossy(&output.stderr)
+            )
+            .into());
         }
+        debug!("Updated {}", remote_url);
 
         Ok(repo_dir)
     } else {
@@ -107,17 +117,32 @@ fn clone_or_update_repo(
     }
 }
 
+/// Revwalk commits are visited roughly newest-first, so once this many
+/// consecutive commits have already been notified about, the rest of the
+/// walk is assumed to have been notified too. A force-push can reshuffle
+/// history near the tip, which is exactly what per-commit `db` lookups
+/// (rather than breaking at a single last-seen sha) are meant to survive;
+/// this just bounds how far back we keep checking.
+const CONSECUTIVE_NOTIFIED_LIMIT: usize = 50;
+
 fn get_new_commits_since(
     repo_path: &PathBuf,
-    last_seen: Option<&str>,
+    db: &db::Db,
+    repo_id: i64,
     max_commits: Option<usize>,
-) -> Result<Vec<CommitInfo>, git2::Error> {
+    include_diffs: bool,
+    start_at: Option<&str>,
+) -> Result<Vec<CommitInfo>, BoxError> {
     let repo = Repository::open(repo_path)?;
     let mut revwalk = repo.revwalk()?;
-    revwalk.push_head()?;
+    match start_at {
+        Some(rev) => revwalk.push(repo.revparse_single(rev)?.id())?,
+        None => revwalk.push_head()?,
+    }
     revwalk.set_sorting(git2::Sort::TIME)?;
 
     let mut commits = Vec::new();
+    let mut consecutive_notified = 0;
     for oid in revwalk {
         if let Some(max) = max_commits {
             if commits.len() >= max {
@@ -129,9 +154,14 @@ fn get_new_commits_since(
         let commit = repo.find_commit(oid)?;
         let id_str = commit.id().to_string();
 
-        if Some(id_str.as_str()) == last_seen {
-            break;
+        if db.is_notified(repo_id, &id_str)? {
+            consecutive_notified += 1;
+            if consecutive_notified >= CONSECUTIVE_NOTIFIED_LIMIT {
+                break;
+            }
+            continue;
         }
+        consecutive_notified = 0;
 
         let time = commit.time().seconds();
         let dt = chrono::DateTime::<chrono::Utc>::from_timestamp(time, 0)
@@ -146,18 +176,94 @@ fn get_new_commits_since(
             }
         }
 
+        let diff = if include_diffs {
+            match diff_commit(&repo, &commit) {
+                Ok(patch) => Some(patch),
+                Err(e) => {
+                    eprintln!("Failed to diff commit {}: {}", id_str, e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
         commits.push(CommitInfo {
             id: id_str,
             date: dt.format("%Y-%m-%d %H:%M:%S").to_string(),
             author: commit.author().name().unwrap_or("Unknown").to_string(),
             message: commit.summary().unwrap_or("").to_string(),
             change_id: change_id,
+            diff,
+            verified: None,
+            author_login: None,
+            author_avatar_url: None,
+            pr_number: None,
+            pr_title: None,
         });
     }
 
     Ok(commits)
 }
 
+/// Renders the unified diff between `commit` and its first parent (or an
+/// empty tree, for a root commit) as a patch string.
+fn diff_commit(repo: &Repository, commit: &git2::Commit) -> Result<String, git2::Error> {
+    let new_tree = commit.tree()?;
+    let old_tree = match commit.parents().next() {
+        Some(parent) => Some(parent.tree()?),
+        None => None,
+    };
+
+    let diff = repo.diff_tree_to_tree(old_tree.as_ref(), Some(&new_tree), None)?;
+
+    let mut patch = String::new();
+    diff.print(git2::DiffFormat::Patch, |_delta, _hunk, line| {
+        let origin = line.origin();
+        if origin == '+' || origin == '-' || origin == ' ' {
+            patch.push(origin);
+        }
+        patch.push_str(&String::from_utf8_lossy(line.content()));
+        true
+    })?;
+
+    Ok(patch)
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// HTML-escapes a unified diff and wraps each `+`/`-` line in a `<span>`
+/// colored by that prefix, for inlining in a report. Other lines, including
+/// the `+++`/`---` file headers, are escaped but left uncolored.
+fn colorize_diff(diff: &str) -> String {
+    let mut out = String::new();
+    for line in diff.lines() {
+        let color = if line.starts_with('+') && !line.starts_with("+++") {
+            Some("#2da44e")
+        } else if line.starts_with('-') && !line.starts_with("---") {
+            Some("#cf222e")
+        } else {
+            None
+        };
+
+        let escaped = escape_html(line);
+        match color {
+            Some(color) => {
+                out.push_str(&format!("<span style=\"color:{}\">{}</span>\n", color, escaped))
+            }
+            None => {
+                out.push_str(&escaped);
+                out.push('\n');
+            }
+        }
+    }
+    out
+}
+
 fn trim_after_domain(url: &str) -> &str {
     let url_no_scheme = if let Some(pos) = url.find("://") {
         &url[pos + 3..]
@@ -174,6 +280,7 @@ fn trim_after_domain(url: &str) -> &str {
 fn build_html_report_with_template(
     repo_commits: &HashMap<String, Vec<CommitInfo>>,
     template_path: Option<&str>,
+    include_diffs: bool,
 ) -> String {
     let mut tables = String::new();
 
@@ -182,7 +289,7 @@ fn build_html_report_with_template(
             continue;
         }
         tables.push_str(&format!(
-            "<h2>Repository: {}</h2><table border=\"1\"><tr><th>ID</th><th>Date</th><th>Author</th><th>Message</th></tr>",
+            "<h2>Repository: {}</h2><table border=\"1\"><tr><th>ID</th><th>Date</th><th>Author</th><th>Message</th><th>Verified</th><th>PR</th></tr>",
             repo
         ));
         for c in commits {
@@ -208,10 +315,51 @@ fn build_html_report_with_template(
                 c.id.clone()
             };
 
+            let author_cell = match &c.author_login {
+                Some(login) => {
+                    let login = escape_html(login);
+                    let avatar = match &c.author_avatar_url {
+                        Some(url) => format!(
+                            "<img src=\"{}\" width=\"16\" height=\"16\"> ",
+                            escape_html(url)
+                        ),
+                        None => String::new(),
+                    };
+                    format!(
+                        "{}<a href=\"{}/{}\">{}</a>",
+                        avatar,
+                        trim_after_domain(repo.trim_end_matches(".git")),
+                        login,
+                        login
+                    )
+                }
+                None => c.author.clone(),
+            };
+
+            let verified_cell = match c.verified {
+                Some(true) => "\u{2705}".to_string(),
+                Some(false) => "\u{274c}".to_string(),
+                None => String::new(),
+            };
+
+            let pr_cell = match (c.pr_number, &c.pr_title) {
+                (Some(number), Some(title)) => format!("#{} {}", number, escape_html(title)),
+                _ => String::new(),
+            };
+
             tables.push_str(&format!(
-                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
-                id_link, c.date, c.author, c.message
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                id_link, c.date, author_cell, c.message, verified_cell, pr_cell
             ));
+
+            if include_diffs {
+                if let Some(diff) = &c.diff {
+                    tables.push_str(&format!(
+                        "<tr><td colspan=\"6\"><details><summary>Diff</summary><pre>{}</pre></details></td></tr>",
+                        colorize_diff(diff)
+                    ));
+                }
+            }
         }
         tables.push_str("</table>");
     }
@@ -228,27 +376,35 @@ fn build_html_report_with_template(
     )
 }
 
-fn send_email(
-    html_body: String,
-    from: &str,
-    to: &str,
-    token: &str,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let email = Message::builder()
-        .from(from.parse::<Mailbox>()?)
-        .to(to.parse::<Mailbox>()?)
-        .subject("Git Commit Notification")
-        .header(ContentType::TEXT_HTML)
-        .body(html_body)?;
-
-    let creds = Credentials::new(from.to_string(), token.to_string());
-
-    let mailer = SmtpTransport::relay("smtp.gmail.com")?
-        .credentials(creds)
-        .build();
-
-    mailer.send(&email)?;
-    Ok(())
+/// Builds a short plain-text summary of a report, for notifiers that can't
+/// embed the rendered HTML (e.g. a JSON webhook payload).
+fn build_text_summary(repo_commits: &HashMap<String, Vec<CommitInfo>>) -> String {
+    let mut summary = String::new();
+    for (repo, commits) in repo_commits {
+        if commits.is_empty() {
+            continue;
+        }
+        summary.push_str(&format!("{}\n", repo));
+        for c in commits {
+            summary.push_str(&format!("  {} {} {}\n", &c.id[..7.min(c.id.len())], c.author, c.message));
+        }
+    }
+    summary
+}
+
+/// Sends `report` to every configured notifier, collecting per-notifier
+/// errors rather than aborting on the first failure. Returns whether at
+/// least one notifier succeeded, so the caller knows whether it's safe to
+/// mark the report's commits as notified.
+fn dispatch_report(notifiers: &[Box<dyn Notifier>], report: &Report) -> bool {
+    let mut any_succeeded = false;
+    for notifier in notifiers {
+        match notifier.notify(report) {
+            Ok(()) => any_succeeded = true,
+            Err(e) => error!("Notifier failed: {}", e),
+        }
+    }
+    any_succeeded
 }
 
 fn load_config(provided_path: Option<&PathBuf>) -> Config {
@@ -284,70 +440,184 @@ fn main() {
 
     let config = load_config(args.config.as_ref());
 
-    let base_cache_dir = config
-        .cache_dir
+    match args.action {
+        Some(Action::Serve { bind }) => {
+            if let Err(e) = webhook::serve(&bind, config) {
+                eprintln!("Webhook server failed: {}", e);
+                std::process::exit(1);
+            }
+        }
+        None => run_poll(args.output, config),
+    }
+}
+
+/// Resolves the cache directory used to store per-repo clones and the
+/// `gitmon.db` notification store, expanding a leading `~` and falling back
+/// to the platform cache dir when unset.
+fn resolve_cache_dir(configured: Option<&str>) -> Result<PathBuf, std::io::Error> {
+    let dir = configured
         .map(|p| {
-            let p = if p.starts_with("~") {
+            if let Some(stripped) = p.strip_prefix('~') {
                 if let Some(home) = dirs::home_dir() {
-                    PathBuf::from(p.replacen("~", home.to_str().unwrap_or(""), 1))
-                } else {
-                    PathBuf::from(p)
+                    return home.join(stripped.trim_start_matches('/'));
                 }
-            } else {
-                PathBuf::from(p)
-            };
-            p
+            }
+            PathBuf::from(p)
         })
         .or_else(|| dirs::cache_dir().map(|p| p.join("gitmon")))
+        .ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "Could not determine cache directory",
+            )
+        })?;
+
+    Ok(dir)
+}
+
+const DEFAULT_CONCURRENCY: usize = 4;
+
+/// Clones/pulls `repo` and walks it for new commits. Run from a worker
+/// thread in `fetch_all_repos`, so failures are returned rather than
+/// printed directly to stderr. Does not mark commits as notified — that
+/// only happens once the caller has actually delivered the report, so a
+/// failed or interrupted delivery leaves commits eligible to be retried.
+fn fetch_one_repo(
+    repo: &RepoConfig,
+    base_cache_dir: &PathBuf,
+    db_path: &PathBuf,
+    max_commits: Option<usize>,
+    include_diffs: bool,
+    api_tokens: &HashMap<String, String>,
+) -> Result<Vec<CommitInfo>, BoxError> {
+    debug!("Checking remote repo: {}", repo.url);
+    let local_path = clone_or_update_repo(&repo.url, base_cache_dir)?;
+
+    let db = db::Db::open(db_path)?;
+    let repo_id = db.repo_id(&repo.url)?;
+    let mut commits = get_new_commits_since(&local_path, &db, repo_id, max_commits, include_diffs, None)?;
+
+    enrich::enrich_commits(repo, &mut commits, api_tokens);
+
+    Ok(commits)
+}
+
+/// Fetches every repo concurrently with a bounded pool of `concurrency`
+/// worker threads, each pulling the next repo off a shared cursor. Each repo
+/// still gets its own cache directory (via `hash_repo_url`), so workers
+/// never contend on the same clone. Per-repo failures are returned in the
+/// result map rather than printed, so callers can decide how to surface
+/// them.
+fn fetch_all_repos(
+    repos: &[RepoConfig],
+    base_cache_dir: &PathBuf,
+    db_path: &PathBuf,
+    max_commits: Option<usize>,
+    include_diffs: bool,
+    concurrency: usize,
+    api_tokens: &HashMap<String, String>,
+) -> HashMap<String, Result<Vec<CommitInfo>, BoxError>> {
+    let next = std::sync::atomic::AtomicUsize::new(0);
+    let (tx, rx) = std::sync::mpsc::channel();
+
+    std::thread::scope(|scope| {
+        for _ in 0..concurrency.min(repos.len().max(1)) {
+            let tx = tx.clone();
+            let next = &next;
+            scope.spawn(move || loop {
+                let idx = next.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                let Some(repo) = repos.get(idx) else {
+                    break;
+                };
+                let result =
+                    fetch_one_repo(repo, base_cache_dir, db_path, max_commits, include_diffs, api_tokens);
+                tx.send((repo.url.clone(), result)).ok();
+            });
+        }
+        drop(tx);
+    });
+
+    rx.into_iter().collect()
+}
+
+fn run_poll(output: Option<PathBuf>, config: Config) {
+    let base_cache_dir = resolve_cache_dir(config.cache_dir.as_deref())
         .expect("Could not determine cache directory");
 
     fs::create_dir_all(&base_cache_dir).expect("Failed to create cache directory");
 
-    let state_file = base_cache_dir.join("state.json");
-    let mut state = load_state(&state_file);
+    let db_path = base_cache_dir.join("gitmon.db");
+    db::Db::open(&db_path).expect("Failed to open state database");
 
-    let mut repo_commits = HashMap::new();
+    let concurrency = config.concurrency.unwrap_or(DEFAULT_CONCURRENCY).max(1);
+    let results = fetch_all_repos(
+        &config.repos,
+        &base_cache_dir,
+        &db_path,
+        config.max_commits,
+        config.include_diffs,
+        concurrency,
+        &config.api_tokens,
+    );
 
-    for remote_url in &config.repos {
-        debug!("Checking remote repo: {}", remote_url);
-        match clone_or_update_repo(remote_url, &base_cache_dir) {
-            Ok(local_path) => {
-                let last_seen_id = state.last_seen.get(remote_url).cloned();
-                match get_new_commits_since(
-                    &local_path,
-                    last_seen_id.as_deref(),
-                    config.max_commits,
-                ) {
-                    Ok(commits) if !commits.is_empty() => {
-                        state
-                            .last_seen
-                            .insert(remote_url.clone(), commits[0].id.clone());
-                        repo_commits.insert(remote_url.clone(), commits);
-                    }
-                    Ok(_) => info!("No new commits in {}", remote_url),
-                    Err(e) => eprintln!("Failed to read commits from {}: {}", remote_url, e),
-                }
+    let mut repo_commits = HashMap::new();
+    for (url, result) in results {
+        match result {
+            Ok(commits) if !commits.is_empty() => {
+                repo_commits.insert(url, commits);
             }
-            Err(e) => eprintln!("Failed to prepare repo {}: {}", remote_url, e),
+            Ok(_) => info!("No new commits in {}", url),
+            Err(e) => error!("Failed to fetch repo {}: {}", url, e),
         }
     }
 
     if !repo_commits.is_empty() {
-        let html = build_html_report_with_template(&repo_commits, config.template_path.as_deref());
+        let html = build_html_report_with_template(
+            &repo_commits,
+            config.template_path.as_deref(),
+            config.include_diffs,
+        );
 
-        if let Some(output_path) = args.output {
+        let delivered = if let Some(output_path) = output {
             match fs::write(&output_path, &html) {
-                Ok(_) => info!("Report written to {:?}", output_path),
-                Err(e) => eprintln!("Failed to write report: {}", e),
+                Ok(_) => {
+                    info!("Report written to {:?}", output_path);
+                    true
+                }
+                Err(e) => {
+                    eprintln!("Failed to write report: {}", e);
+                    false
+                }
             }
         } else {
-            match send_email(html, &config.from, &config.to, &config.token) {
-                Ok(_) => info!("Email sent successfully."),
-                Err(e) => eprintln!("Failed to send email: {}", e),
+            let summary = build_text_summary(&repo_commits);
+            let notifiers = build_notifiers(&config.notifiers);
+            dispatch_report(&notifiers, &Report { html, summary })
+        };
+
+        if delivered {
+            match db::Db::open(&db_path) {
+                Ok(db) => {
+                    for (url, commits) in &repo_commits {
+                        let repo_id = match db.repo_id(url) {
+                            Ok(id) => id,
+                            Err(e) => {
+                                error!("Failed to look up repo id for {}: {}", url, e);
+                                continue;
+                            }
+                        };
+                        for commit in commits {
+                            if let Err(e) = db.mark_notified(repo_id, commit) {
+                                error!("Failed to record notified commit {} for {}: {}", commit.id, url, e);
+                            }
+                        }
+                    }
+                }
+                Err(e) => error!("Failed to reopen state database to record notified commits: {}", e),
             }
+        } else {
+            info!("Report not delivered; commits will be retried next run");
         }
-
-        save_state(&state, &state_file);
     } else {
         info!("No new commits found.");
     }
@@ -0,0 +1,141 @@
+//! Notification backends: a `Notifier` trait so a report can fan out to any
+//! mix of configured SMTP/webhook destinations.
+
+use lettre::{
+    message::{header::ContentType, Mailbox, Mailboxes},
+    transport::smtp::authentication::Credentials,
+    Message, SmtpTransport, Transport,
+};
+use serde::Deserialize;
+
+/// A rendered report ready to hand to a notifier. Backends that can't embed
+/// HTML (e.g. a JSON webhook) fall back to `summary`.
+pub struct Report {
+    pub html: String,
+    pub summary: String,
+}
+
+pub trait Notifier {
+    fn notify(&self, report: &Report) -> Result<(), Box<dyn std::error::Error>>;
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NotifierConfig {
+    Smtp {
+        host: String,
+        #[serde(default = "default_smtp_port")]
+        port: u16,
+        #[serde(default)]
+        starttls: bool,
+        username: String,
+        password: String,
+        from: String,
+        to: Vec<String>,
+    },
+    Webhook {
+        url: String,
+        /// POST the JSON summary instead of the rendered HTML body.
+        #[serde(default)]
+        json: bool,
+    },
+}
+
+fn default_smtp_port() -> u16 {
+    587
+}
+
+pub fn build_notifiers(configs: &[NotifierConfig]) -> Vec<Box<dyn Notifier>> {
+    configs
+        .iter()
+        .map(|c| -> Box<dyn Notifier> {
+            match c {
+                NotifierConfig::Smtp {
+                    host,
+                    port,
+                    starttls,
+                    username,
+                    password,
+                    from,
+                    to,
+                } => Box::new(SmtpNotifier {
+                    host: host.clone(),
+                    port: *port,
+                    starttls: *starttls,
+                    username: username.clone(),
+                    password: password.clone(),
+                    from: from.clone(),
+                    to: to.clone(),
+                }),
+                NotifierConfig::Webhook { url, json } => Box::new(WebhookNotifier {
+                    url: url.clone(),
+                    json: *json,
+                }),
+            }
+        })
+        .collect()
+}
+
+pub struct SmtpNotifier {
+    host: String,
+    port: u16,
+    starttls: bool,
+    username: String,
+    password: String,
+    from: String,
+    to: Vec<String>,
+}
+
+impl Notifier for SmtpNotifier {
+    fn notify(&self, report: &Report) -> Result<(), Box<dyn std::error::Error>> {
+        let mailboxes: Mailboxes = self.to.join(", ").parse()?;
+
+        let mut builder = Message::builder().from(self.from.parse::<Mailbox>()?);
+        for mailbox in mailboxes {
+            builder = builder.to(mailbox);
+        }
+
+        let email = builder
+            .subject("Git Commit Notification")
+            .header(ContentType::TEXT_HTML)
+            .body(report.html.clone())?;
+
+        let creds = Credentials::new(self.username.clone(), self.password.clone());
+
+        let transport = if self.starttls {
+            SmtpTransport::starttls_relay(&self.host)?
+        } else {
+            SmtpTransport::relay(&self.host)?
+        };
+
+        transport
+            .port(self.port)
+            .credentials(creds)
+            .build()
+            .send(&email)?;
+
+        Ok(())
+    }
+}
+
+pub struct WebhookNotifier {
+    url: String,
+    json: bool,
+}
+
+impl Notifier for WebhookNotifier {
+    fn notify(&self, report: &Report) -> Result<(), Box<dyn std::error::Error>> {
+        let request = ureq::post(&self.url);
+
+        let response = if self.json {
+            request.send_json(ureq::json!({ "summary": report.summary }))
+        } else {
+            request
+                .set("Content-Type", "text/html")
+                .send_string(&report.html)
+        };
+
+        response.map_err(|e| format!("webhook POST to {} failed: {}", self.url, e))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,238 @@
+//! GitHub push webhook server: verifies `X-Hub-Signature-256`, maps the
+//! pushed repo to a configured remote, and runs the usual
+//! fetch/report/notify pipeline for just that repo instead of polling.
+
+use hmac::{Hmac, Mac};
+use log::{debug, error, info, warn};
+use serde::Deserialize;
+use sha2::Sha256;
+use std::collections::HashMap;
+use tiny_http::{Response, Server};
+
+use crate::db::Db;
+use crate::notifier::{build_notifiers, Report};
+use crate::{build_html_report_with_template, build_text_summary, clone_or_update_repo, get_new_commits_since, Config};
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Debug, Deserialize)]
+struct PushEvent {
+    repository: PushRepository,
+    after: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PushRepository {
+    full_name: String,
+}
+
+/// Run the webhook HTTP server until the process is killed.
+pub fn serve(bind: &str, config: Config) -> Result<(), Box<dyn std::error::Error>> {
+    let server = Server::http(bind).map_err(|e| format!("failed to bind {}: {}", bind, e))?;
+    info!("Listening for GitHub push webhooks on {}", bind);
+
+    for mut request in server.incoming_requests() {
+        let mut body = Vec::new();
+        if let Err(e) = request.as_reader().read_to_end(&mut body) {
+            error!("Failed to read webhook body: {}", e);
+            let _ = request.respond(Response::empty(400));
+            continue;
+        }
+
+        let signature = request
+            .headers()
+            .iter()
+            .find(|h| h.field.as_str().as_str().eq_ignore_ascii_case("X-Hub-Signature-256"))
+            .map(|h| h.value.as_str().to_string());
+
+        match handle_push(&config, &body, signature.as_deref()) {
+            Ok(()) => {
+                let _ = request.respond(Response::empty(200));
+            }
+            Err(WebhookError::Unauthorized) => {
+                warn!("Rejected webhook delivery with invalid signature");
+                let _ = request.respond(Response::empty(401));
+            }
+            Err(e) => {
+                error!("Failed to handle webhook delivery: {}", e);
+                let _ = request.respond(Response::empty(500));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Debug)]
+enum WebhookError {
+    Unauthorized,
+    Other(String),
+}
+
+impl std::fmt::Display for WebhookError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WebhookError::Unauthorized => write!(f, "signature verification failed"),
+            WebhookError::Other(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for WebhookError {}
+
+fn handle_push(config: &Config, body: &[u8], signature: Option<&str>) -> Result<(), WebhookError> {
+    let event: PushEvent = serde_json::from_slice(body)
+        .map_err(|e| WebhookError::Other(format!("invalid payload: {}", e)))?;
+
+    let repo = config
+        .repos
+        .iter()
+        .find(|r| full_name_matches(&r.url, &event.repository.full_name))
+        .ok_or_else(|| {
+            WebhookError::Other(format!(
+                "push for unconfigured repo {}",
+                event.repository.full_name
+            ))
+        })?;
+
+    let secret = repo
+        .webhook_secret
+        .as_deref()
+        .ok_or_else(|| WebhookError::Other(format!("no webhook_secret configured for {}", repo.url)))?;
+
+    verify_signature(secret, body, signature).ok_or(WebhookError::Unauthorized)?;
+
+    debug!(
+        "Verified push to {} (new tip {})",
+        event.repository.full_name, event.after
+    );
+
+    run_pipeline(config, repo, &event.after).map_err(|e| WebhookError::Other(e.to_string()))
+}
+
+/// Verifies `sha256=<hex>` against the HMAC-SHA256 of `body` keyed by
+/// `secret`, in constant time. Returns `None` on any mismatch or malformed
+/// header, deliberately collapsing the failure modes into one outcome.
+fn verify_signature(secret: &str, body: &[u8], header: Option<&str>) -> Option<()> {
+    let header = header?;
+    let hex_sig = header.strip_prefix("sha256=")?;
+    let expected = hex::decode(hex_sig).ok()?;
+
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).ok()?;
+    mac.update(body);
+
+    mac.verify_slice(&expected).ok()
+}
+
+fn full_name_matches(remote_url: &str, full_name: &str) -> bool {
+    let trimmed = remote_url.trim_end_matches(".git").trim_end_matches('/');
+    trimmed == full_name || trimmed.ends_with(&format!("/{full_name}"))
+}
+
+fn run_pipeline(
+    config: &Config,
+    repo: &crate::RepoConfig,
+    pushed_tip: &str,
+) -> Result<(), crate::BoxError> {
+    let base_cache_dir = crate::resolve_cache_dir(config.cache_dir.as_deref())?;
+    std::fs::create_dir_all(&base_cache_dir)?;
+
+    let db = Db::open(&base_cache_dir.join("gitmon.db"))?;
+
+    let local_path = clone_or_update_repo(&repo.url, &base_cache_dir)?;
+    let repo_id = db.repo_id(&repo.url)?;
+    let mut commits = get_new_commits_since(
+        &local_path,
+        &db,
+        repo_id,
+        config.max_commits,
+        config.include_diffs,
+        Some(pushed_tip),
+    )?;
+
+    if commits.is_empty() {
+        info!(
+            "Push to {} (new tip {}) had no new commits to report",
+            repo.url, pushed_tip
+        );
+        return Ok(());
+    }
+
+    crate::enrich::enrich_commits(repo, &mut commits, &config.api_tokens);
+
+    let mut repo_commits = HashMap::new();
+    repo_commits.insert(repo.url.clone(), commits);
+
+    let html = build_html_report_with_template(
+        &repo_commits,
+        config.template_path.as_deref(),
+        config.include_diffs,
+    );
+    let summary = build_text_summary(&repo_commits);
+
+    let mut any_succeeded = false;
+    for notifier in build_notifiers(&config.notifiers) {
+        match notifier.notify(&Report {
+            html: html.clone(),
+            summary: summary.clone(),
+        }) {
+            Ok(()) => any_succeeded = true,
+            Err(e) => error!("Notifier failed for push to {}: {}", repo.url, e),
+        }
+    }
+
+    if any_succeeded {
+        for commit in &repo_commits[&repo.url] {
+            db.mark_notified(repo_id, commit)?;
+        }
+    } else {
+        info!(
+            "No notifier delivered the report for {}; commits will be retried on next push",
+            repo.url
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_name_matches_exact_and_suffix() {
+        assert!(full_name_matches("https://github.com/foo/bar", "foo/bar"));
+        assert!(full_name_matches("https://github.com/foo/bar.git", "foo/bar"));
+        assert!(full_name_matches("https://github.com/foo/bar/", "foo/bar"));
+    }
+
+    #[test]
+    fn full_name_matches_rejects_suffix_confusion() {
+        assert!(!full_name_matches("https://github.com/xfoo/bar", "foo/bar"));
+        assert!(!full_name_matches("https://github.com/foo/bar", "foo/barbaz"));
+    }
+
+    #[test]
+    fn verify_signature_accepts_valid_hmac() {
+        let secret = "s3cr3t";
+        let body = b"payload";
+
+        let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(body);
+        let signature = format!("sha256={}", hex::encode(mac.finalize().into_bytes()));
+
+        assert!(verify_signature(secret, body, Some(&signature)).is_some());
+    }
+
+    #[test]
+    fn verify_signature_rejects_wrong_secret_or_missing_header() {
+        let body = b"payload";
+        let mut mac = HmacSha256::new_from_slice(b"s3cr3t".as_ref()).unwrap();
+        mac.update(body);
+        let signature = format!("sha256={}", hex::encode(mac.finalize().into_bytes()));
+
+        assert!(verify_signature("wrong-secret", body, Some(&signature)).is_none());
+        assert!(verify_signature("s3cr3t", body, None).is_none());
+        assert!(verify_signature("s3cr3t", body, Some("not-a-valid-header")).is_none());
+    }
+}